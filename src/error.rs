@@ -58,3 +58,57 @@ impl From<Box<dyn std::error::Error>> for DocimaError {
         Self::StdError(err)
     }
 }
+
+/// A non-fatal diagnostic surfaced by `ImageFile::generate` and its sibling
+/// methods.
+///
+/// Unlike [`DocimaError`], a warning doesn't fail the build — it calls
+/// attention to something still worth fixing, like a missing `alt`
+/// attribute. Returned warnings are also printed as `cargo:warning=` lines,
+/// so build scripts surface them without inspecting the returned `Vec`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DocimaWarning {
+    /// No `alt` attribute was set, hurting accessibility.
+    MissingAlt,
+
+    /// `wrapper_href` or `wrapper_target` was set, but `wrapper` isn't
+    /// `"a"`, so the attribute is silently ignored.
+    IgnoredWrapperAnchorAttr,
+
+    /// The generated HTML exceeds the size threshold docima considers
+    /// reasonable to embed inline.
+    LargePayload {
+        /// The generated HTML size, in bytes.
+        bytes: usize,
+        /// The threshold it exceeded, in bytes.
+        threshold: usize,
+    },
+
+    /// The generator left its output entirely at its zero-initialized
+    /// value, usually a sign it returned early or never wrote to its
+    /// buffer.
+    ///
+    /// This is a heuristic: a generator that legitimately produces an
+    /// all-zero buffer (e.g. a pure black RGB image) also triggers it.
+    EmptyOutput,
+}
+
+impl fmt::Display for DocimaWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DocimaWarning::*;
+        match self {
+            MissingAlt => write!(f, "missing `alt` attribute, hurting accessibility"),
+            IgnoredWrapperAnchorAttr => write!(
+                f,
+                "`wrapper_href`/`wrapper_target` set but `wrapper` isn't \"a\", ignored"
+            ),
+            LargePayload { bytes, threshold } => write!(
+                f,
+                "generated HTML is {} bytes, exceeding the {} byte threshold",
+                bytes, threshold
+            ),
+            EmptyOutput => write!(f, "generator left its output entirely unfilled"),
+        }
+    }
+}
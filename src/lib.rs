@@ -38,14 +38,23 @@
 //! [provided example](https://github.com/andamira/docima/tree/master/example).
 
 use data_encoding::BASE64_MIME;
+use sha2::{Digest, Sha256};
 use std::{
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, read_to_string, File},
     io::{Cursor, Write},
-    path::Path,
+    path::{Path, PathBuf},
 };
 
 mod error;
-pub use error::{DocimaError, DocimaResult, StdResult};
+pub use error::{DocimaError, DocimaResult, DocimaWarning, StdResult};
+
+/// The generated HTML size, in bytes, above which [`DocimaWarning::LargePayload`]
+/// is raised.
+const LARGE_PAYLOAD_THRESHOLD: usize = 100_000;
+
+/// Where [`ImageFile::manifest`] records generated images, relative to the
+/// project root.
+const MANIFEST_PATH: &str = "target/docima-manifest.json";
 
 /// An image file generator.
 ///
@@ -98,6 +107,22 @@ pub struct ImageFile {
 
     // controls whether existing images should be overwritten.
     overwrite: bool,
+
+    // whether a generated SVG is embedded as raw inline markup (true) or as
+    // a base64 data URI (false). Only relevant to `generate_svg`.
+    svg_inline: bool,
+
+    // number of frames and per-frame delay, in milliseconds, for an
+    // animated PNG. Only relevant to `generate_animated`.
+    frames: u32,
+    frame_delay: u32,
+
+    // whether the PNG buffer carries an alpha channel (RGBA8, 4 bytes per
+    // pixel) instead of plain RGB8 (3 bytes per pixel).
+    alpha: bool,
+
+    // whether this image is recorded in the shared `docima-manifest.json`.
+    manifest: bool,
 }
 
 impl Default for ImageFile {
@@ -121,6 +146,11 @@ impl Default for ImageFile {
             wrapper_target: String::default(),
             // MAYBE set with feature
             overwrite: false,
+            svg_inline: true,
+            frames: 1,
+            frame_delay: 100,
+            alpha: false,
+            manifest: false,
         }
     }
 }
@@ -132,11 +162,9 @@ impl ImageFile {
         Self::default()
     }
 
-    /// Finishes the image, calling the generator function and saving the file.
-    pub fn generate(
-        self,
-        generator: impl Fn(&mut Vec<u8>, u32, u32) -> StdResult<()>,
-    ) -> DocimaResult<()> {
+    /// Validates the required fields, resolves the output path and makes
+    /// sure its parent directory exists.
+    fn prepare(&self) -> DocimaResult<PathBuf> {
         if self.width == 0 {
             return Err(DocimaError::MissingField("width".into()));
         } else if self.height == 0 {
@@ -145,11 +173,7 @@ impl ImageFile {
             return Err(DocimaError::MissingField("path".into()));
         }
 
-        // TODO WIP
-
-        // prepare the output path
-        let filepath_str = root_path(&self.path);
-        let filepath = Path::new(&filepath_str);
+        let filepath = PathBuf::from(root_path(&self.path));
         let dirpath = filepath.parent().ok_or_else(|| {
             DocimaError::Custom(format![
                 "no parent: `{}`",
@@ -160,92 +184,629 @@ impl ImageFile {
             create_dir_all(dirpath)?;
         }
 
-        // Don't generate this image if the file already exists and we're not overwriting.
-        if filepath.exists() && !self.overwrite {
+        Ok(filepath)
+    }
+
+    /// Writes `content` to `filepath`, prefixed with a `docima:sha256`
+    /// content-hash comment.
+    ///
+    /// The hash covers `raw_data` (the generator's raw output buffer, or
+    /// buffers for a themed pair) together with this image's configuration.
+    /// Unless [`overwrite(true)`][Self::overwrite] is set, the write is
+    /// skipped when the hash matches the one already embedded in
+    /// `filepath`, keeping committed docs diff-stable across `cargo doc`
+    /// runs that produce identical output.
+    fn finalize(
+        &self,
+        filepath: PathBuf,
+        raw_data: &[&[u8]],
+        content: String,
+        format: &str,
+    ) -> DocimaResult<()> {
+        let hash = self.content_hash(raw_data, format);
+
+        self.record_manifest(format, &hash)?;
+
+        if !self.overwrite && Self::cached_hash(&filepath).as_deref() == Some(hash.as_str()) {
+            return Ok(());
+        }
+
+        let content = format!["<!-- docima:sha256={} -->\n{}", hash, content];
+        let mut outfile = File::create(filepath)?;
+        write!(outfile, "{}", content)?;
+        Ok(())
+    }
+
+    /// Appends (or replaces, keyed by `path`) this image's entry in the
+    /// shared `docima-manifest.json`, when [`manifest(true)`][Self::manifest]
+    /// is set. A no-op otherwise.
+    fn record_manifest(&self, format: &str, hash: &str) -> DocimaResult<()> {
+        if !self.manifest {
             return Ok(());
         }
 
-        let mut rgb_buffer = vec![0; self.width as usize * self.height as usize * 3];
+        let manifest_path = PathBuf::from(root_path(MANIFEST_PATH));
+        if let Some(dir) = manifest_path.parent() {
+            if !dir.exists() {
+                create_dir_all(dir)?;
+            }
+        }
+
+        let mut entries = if manifest_path.exists() {
+            split_json_objects(&read_to_string(&manifest_path)?)
+        } else {
+            Vec::new()
+        };
+
+        let path_marker = format!["\"path\":\"{}\"", json_escape(&self.path)];
+        entries.retain(|entry| !entry.contains(&path_marker));
+        entries.push(self.manifest_entry_json(format, hash));
+
+        let manifest = format!["[\n  {}\n]\n", entries.join(",\n  ")];
+        let mut outfile = File::create(&manifest_path)?;
+        write!(outfile, "{}", manifest)?;
+        Ok(())
+    }
+
+    /// Serializes this image's manifest entry as a single JSON object.
+    fn manifest_entry_json(&self, format: &str, hash: &str) -> String {
+        format![
+            "{{\"path\":\"{}\",\"width\":{},\"height\":{},\"format\":\"{}\",\"wrapper\":\"{}\",\
+             \"alt\":\"{}\",\"title\":\"{}\",\"id\":\"{}\",\"class\":\"{}\",\"style\":\"{}\",\
+             \"hash\":\"{}\"}}",
+            json_escape(&self.path),
+            self.width,
+            self.height,
+            format,
+            json_escape(&self.wrapper),
+            json_escape(&self.alt),
+            json_escape(&self.title),
+            json_escape(&self.id),
+            json_escape(&self.class),
+            json_escape(&self.style),
+            hash,
+        ]
+    }
+
+    /// Computes a hex-encoded sha256 hash of `raw_data` combined with a
+    /// serialization of every field that affects the generated HTML.
+    fn content_hash(&self, raw_data: &[&[u8]], format: &str) -> String {
+        let mut hasher = Sha256::new();
+        for chunk in raw_data {
+            hasher.update(chunk);
+        }
+        hasher.update(self.config_fingerprint(format).as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!["{:02x}", byte])
+            .collect()
+    }
+
+    /// A stable serialization of every field that affects the generated
+    /// HTML, excluding `overwrite` itself, together with `format` (the
+    /// output kind, e.g. `"png"`/`"apng"`/`"svg"`/`"themed-png"`).
+    ///
+    /// `svg_inline` and `frame_delay` don't show up anywhere in `raw_data`
+    /// (they reshape the surrounding HTML, not the rendered buffer), so they
+    /// must be folded in here or toggling them would leave cached output
+    /// stale.
+    fn config_fingerprint(&self, format: &str) -> String {
+        format![
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            format,
+            self.width,
+            self.height,
+            self.path,
+            self.alt,
+            self.title,
+            self.id,
+            self.class,
+            self.style,
+            self.wrapper,
+            self.wrapper_alt,
+            self.wrapper_title,
+            self.wrapper_id,
+            self.wrapper_class,
+            self.wrapper_style,
+            self.wrapper_href,
+            self.wrapper_target,
+            self.svg_inline,
+            self.frames,
+            self.frame_delay,
+            self.alpha,
+        ]
+    }
+
+    /// Reads back the `docima:sha256` hash embedded in a previously
+    /// generated file, if any.
+    fn cached_hash(filepath: &Path) -> Option<String> {
+        let existing = read_to_string(filepath).ok()?;
+        existing
+            .lines()
+            .next()?
+            .strip_prefix("<!-- docima:sha256=")?
+            .strip_suffix(" -->")
+            .map(str::to_owned)
+    }
+
+    /// Diagnoses config-level issues that don't depend on the rendered
+    /// output: a missing `alt` attribute, and `wrapper_href`/`wrapper_target`
+    /// set on a non-anchor `wrapper`.
+    fn config_warnings(&self) -> Vec<DocimaWarning> {
+        let mut warnings = Vec::new();
+        if self.alt.is_empty() {
+            warnings.push(DocimaWarning::MissingAlt);
+        }
+        if self.wrapper != "a" && (!self.wrapper_href.is_empty() || !self.wrapper_target.is_empty())
+        {
+            warnings.push(DocimaWarning::IgnoredWrapperAnchorAttr);
+        }
+        warnings
+    }
+
+    /// Diagnoses a `content` that exceeds [`LARGE_PAYLOAD_THRESHOLD`].
+    fn payload_warnings(content_len: usize) -> Vec<DocimaWarning> {
+        if content_len > LARGE_PAYLOAD_THRESHOLD {
+            vec![DocimaWarning::LargePayload {
+                bytes: content_len,
+                threshold: LARGE_PAYLOAD_THRESHOLD,
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Diagnoses a generator that left one of `buffers` entirely at its
+    /// zero-initialized value, the usual sign a generator returned early or
+    /// never wrote to its buffer.
+    ///
+    /// This is a heuristic, not a guarantee: a generator that legitimately
+    /// fills a buffer with all-zero pixels (e.g. a pure black RGB image)
+    /// also trips it. There's no cheap way to tell "never written" apart
+    /// from "written to all zero" without a distinct sentinel the generator
+    /// is guaranteed not to produce, so false positives on all-black output
+    /// are an accepted trade-off.
+    fn buffer_warnings(buffers: &[&[u8]]) -> Vec<DocimaWarning> {
+        if buffers
+            .iter()
+            .any(|buffer| buffer.iter().all(|&byte| byte == 0))
+        {
+            vec![DocimaWarning::EmptyOutput]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Prints each warning as a `cargo:warning=` line, so build scripts
+    /// surface them without inspecting the returned `Vec`.
+    fn emit_warnings(warnings: &[DocimaWarning]) {
+        for warning in warnings {
+            println!("cargo:warning=docima: {}", warning);
+        }
+    }
+
+    /// Builds the `<img>`-style attribute string shared by the raster and
+    /// base64-embedded SVG paths: `id`, `class`, `alt`, `title` and `style`.
+    fn img_attrs(&self) -> String {
+        let mut attrs = String::new();
+        if !self.id.is_empty() {
+            attrs += &format!["id=\"{}\" ", self.id];
+        }
+        if !self.class.is_empty() {
+            attrs += &format!["class=\"{}\" ", self.class];
+        }
+        if !self.alt.is_empty() {
+            attrs += &format!["alt=\"{}\" ", self.alt];
+        }
+        if !self.title.is_empty() {
+            attrs += &format!["title=\"{}\" ", self.title];
+        }
+        if !self.style.is_empty() {
+            attrs += &format!["style=\"{}\" ", self.style];
+        }
+        attrs
+    }
+
+    /// Wraps `content` with the configured `wrapper` tag, if any.
+    fn wrap(&self, content: String) -> String {
+        if self.wrapper.is_empty() {
+            return content;
+        }
+
+        let mut wrapper_open = format!["<{0} ", self.wrapper];
+
+        // add the wrapper attributes to the opening tag
+        if !self.wrapper_id.is_empty() {
+            wrapper_open += &format!["id=\"{}\" ", self.wrapper_id];
+        }
+        if !self.wrapper_class.is_empty() {
+            wrapper_open += &format!["class=\"{}\" ", self.wrapper_class];
+        }
+        if !self.wrapper_alt.is_empty() {
+            wrapper_open += &format!["alt=\"{}\" ", self.wrapper_alt];
+        }
+        if !self.wrapper_title.is_empty() {
+            wrapper_open += &format!["title=\"{}\" ", self.wrapper_title];
+        }
+        if !self.wrapper_style.is_empty() {
+            wrapper_open += &format!["style=\"{}\" ", self.wrapper_style];
+        }
+        // anchor specific attributes
+        if self.wrapper == "a" {
+            if !self.wrapper_href.is_empty() {
+                wrapper_open += &format!["href=\"{}\" ", self.wrapper_href];
+            }
+            if !self.wrapper_target.is_empty() {
+                wrapper_open += &format!["target=\"{}\" ", self.wrapper_target];
+            }
+        }
+        wrapper_open += ">";
+
+        format!["{0}{1}</{2}>", wrapper_open, content, self.wrapper]
+    }
+
+    /// The number of bytes per pixel the generator must fill: 4 (RGBA8) when
+    /// [`alpha(true)`][Self::alpha] is set, 3 (RGB8) otherwise.
+    fn pixel_stride(&self) -> usize {
+        if self.alpha {
+            4
+        } else {
+            3
+        }
+    }
+
+    /// The PNG color type matching [`pixel_stride`][Self::pixel_stride].
+    fn png_color_type(&self) -> png::ColorType {
+        if self.alpha {
+            png::ColorType::Rgba
+        } else {
+            png::ColorType::Rgb
+        }
+    }
+
+    /// Allocates a buffer sized for one frame and hands it to `generator`,
+    /// checking it comes back at the expected length for the configured
+    /// width, height and [`pixel_stride`][Self::pixel_stride].
+    fn render_frame(
+        &self,
+        generator: impl Fn(&mut Vec<u8>, u32, u32) -> StdResult<()>,
+    ) -> DocimaResult<Vec<u8>> {
+        let expected_len = self.width as usize * self.height as usize * self.pixel_stride();
+        let mut buffer = vec![0; expected_len];
+        generator(&mut buffer, self.width, self.height)?;
+        if buffer.len() != expected_len {
+            return Err(DocimaError::Custom(format![
+                "generator left the buffer at {} bytes, expected {} for a {}x{} {} image",
+                buffer.len(),
+                expected_len,
+                self.width,
+                self.height,
+                if self.alpha { "RGBA8" } else { "RGB8" },
+            ]));
+        }
+        Ok(buffer)
+    }
 
-        // generate the image as rgb8 using the provided function
-        generator(&mut rgb_buffer, self.width, self.height)?;
+    /// Renders `generator` into a buffer and PNG-encodes it, shared by
+    /// [`generate`][Self::generate] and [`generate_themed`
+    /// ][Self::generate_themed]. Returns the raw buffer (used for content
+    /// hashing) alongside the base64-encoded PNG data.
+    fn render_png_base64(
+        &self,
+        generator: impl Fn(&mut Vec<u8>, u32, u32) -> StdResult<()>,
+    ) -> DocimaResult<(Vec<u8>, String)> {
+        let buffer = self.render_frame(generator)?;
 
         // encode the image as png data in a memory buffer
         let mut png_buffer = Vec::<u8>::new();
         {
             let cursor_buffer = Cursor::new(&mut png_buffer);
             let mut encoder = png::Encoder::new(cursor_buffer, self.width, self.height);
-            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_color(self.png_color_type());
             encoder.set_depth(png::BitDepth::Eight);
             encoder.set_compression(png::Compression::Best);
 
             let mut writer = encoder.write_header()?;
-            writer.write_image_data(&rgb_buffer)?;
+            writer.write_image_data(&buffer)?;
+        }
+
+        Ok((buffer, BASE64_MIME.encode(png_buffer.as_slice())))
+    }
+
+    /// Finishes the image, calling the generator function and saving the
+    /// file.
+    ///
+    /// Returns any non-fatal [`DocimaWarning`]s raised along the way (also
+    /// printed as `cargo:warning=` lines).
+    pub fn generate(
+        self,
+        generator: impl Fn(&mut Vec<u8>, u32, u32) -> StdResult<()>,
+    ) -> DocimaResult<Vec<DocimaWarning>> {
+        let filepath = self.prepare()?;
+
+        let (rgb_buffer, base64) = self.render_png_base64(generator)?;
+
+        // embed the base64 data in HTML tag
+        let content = format![
+            "<img src=\"data:image/png;base64,\n{}\" {}/>",
+            base64,
+            self.img_attrs()
+        ];
+
+        // add the wrapper HTML tag
+        let content = self.wrap(content);
+
+        let mut warnings = self.config_warnings();
+        warnings.extend(Self::payload_warnings(content.len()));
+        warnings.extend(Self::buffer_warnings(&[&rgb_buffer]));
+        Self::emit_warnings(&warnings);
+
+        self.finalize(filepath, &[&rgb_buffer], content, "png")?;
+        Ok(warnings)
+    }
+
+    /// Finishes the image, calling `generator` once per frame and encoding
+    /// the results as an animated PNG (APNG).
+    ///
+    /// `generator` receives the frame buffer, the zero-based frame index,
+    /// and the image dimensions. Configure the frame count and per-frame
+    /// delay with [`frames`][Self::frames] and [`frame_delay`
+    /// ][Self::frame_delay] before calling this; the animation loops
+    /// forever.
+    ///
+    /// Returns any non-fatal [`DocimaWarning`]s raised along the way (also
+    /// printed as `cargo:warning=` lines).
+    pub fn generate_animated(
+        self,
+        generator: impl Fn(&mut Vec<u8>, u32, u32, u32) -> StdResult<()>,
+    ) -> DocimaResult<Vec<DocimaWarning>> {
+        if self.frames == 0 {
+            return Err(DocimaError::MissingField("frames".into()));
+        }
+        if self.frame_delay > u16::MAX as u32 {
+            return Err(DocimaError::Custom(format![
+                "frame_delay of {} ms exceeds the APNG limit of {} ms",
+                self.frame_delay,
+                u16::MAX,
+            ]));
+        }
+
+        let filepath = self.prepare()?;
+
+        let mut frame_buffers = Vec::with_capacity(self.frames as usize);
+        for frame_index in 0..self.frames {
+            let buffer = self.render_frame(|buffer, w, h| generator(buffer, frame_index, w, h))?;
+            frame_buffers.push(buffer);
+        }
+
+        let mut png_buffer = Vec::<u8>::new();
+        {
+            let cursor_buffer = Cursor::new(&mut png_buffer);
+            let mut encoder = png::Encoder::new(cursor_buffer, self.width, self.height);
+            encoder.set_color(self.png_color_type());
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder.set_compression(png::Compression::Best);
+            // loop forever
+            encoder.set_animated(self.frames, 0)?;
+
+            let mut writer = encoder.write_header()?;
+            writer.set_frame_delay(self.frame_delay as u16, 1000)?;
+            for frame in &frame_buffers {
+                writer.write_image_data(frame)?;
+            }
         }
 
         // encode the png data as base64 data
         let base64 = BASE64_MIME.encode(png_buffer.as_slice());
 
         // embed the base64 data in HTML tag
-        let mut content = format!["<img src=\"data:image/png;base64,\n{}\" ", base64];
+        let content = format![
+            "<img src=\"data:image/png;base64,\n{}\" {}/>",
+            base64,
+            self.img_attrs()
+        ];
+
+        // add the wrapper HTML tag
+        let content = self.wrap(content);
+
+        let raw_data: Vec<&[u8]> = frame_buffers.iter().map(Vec::as_slice).collect();
+
+        let mut warnings = self.config_warnings();
+        warnings.extend(Self::payload_warnings(content.len()));
+        warnings.extend(Self::buffer_warnings(&raw_data));
+        Self::emit_warnings(&warnings);
+
+        self.finalize(filepath, &raw_data, content, "apng")?;
+        Ok(warnings)
+    }
+
+    /// Finishes the image like [`generate`][Self::generate], but renders two
+    /// PNG variants, one per theme, and embeds both `<img>` tags plus a
+    /// scoped `<style>` block that shows only the one matching the reader's
+    /// rustdoc theme.
+    ///
+    /// Selection works both automatically, via `prefers-color-scheme`, and
+    /// manually, by keying off the `data-theme` attribute rustdoc sets on
+    /// `<html>` when a reader picks a theme explicitly from the settings
+    /// menu.
+    ///
+    /// Returns any non-fatal [`DocimaWarning`]s raised along the way (also
+    /// printed as `cargo:warning=` lines).
+    pub fn generate_themed(
+        self,
+        light_generator: impl Fn(&mut Vec<u8>, u32, u32) -> StdResult<()>,
+        dark_generator: impl Fn(&mut Vec<u8>, u32, u32) -> StdResult<()>,
+    ) -> DocimaResult<Vec<DocimaWarning>> {
+        let filepath = self.prepare()?;
+
+        let (light_buffer, light_base64) = self.render_png_base64(light_generator)?;
+        let (dark_buffer, dark_base64) = self.render_png_base64(dark_generator)?;
+
+        let uid = self.css_uid();
+        let light_class = format!["docima-light-{}", uid];
+        let dark_class = format!["docima-dark-{}", uid];
+
+        let light_img = format![
+            "<img src=\"data:image/png;base64,\n{}\" {}/>",
+            light_base64,
+            self.themed_img_attrs("light", &light_class)
+        ];
+        let dark_img = format![
+            "<img src=\"data:image/png;base64,\n{}\" {}/>",
+            dark_base64,
+            self.themed_img_attrs("dark", &dark_class)
+        ];
+
+        let style = format![
+            "<style>\
+             .{light}{{display:inline}} .{dark}{{display:none}}\
+             @media (prefers-color-scheme:dark){{.{light}{{display:none}} .{dark}{{display:inline}}}}\
+             html[data-theme=\"dark\"] .{light},html[data-theme=\"ayu\"] .{light}{{display:none}}\
+             html[data-theme=\"dark\"] .{dark},html[data-theme=\"ayu\"] .{dark}{{display:inline}}\
+             html[data-theme=\"light\"] .{light}{{display:inline}} html[data-theme=\"light\"] .{dark}{{display:none}}\
+             </style>",
+            light = light_class,
+            dark = dark_class,
+        ];
+
+        let content = self.wrap(format!["{}{}{}", style, light_img, dark_img]);
 
-        // add the <img> attributes
+        let mut warnings = self.config_warnings();
+        warnings.extend(Self::payload_warnings(content.len()));
+        warnings.extend(Self::buffer_warnings(&[&light_buffer, &dark_buffer]));
+        Self::emit_warnings(&warnings);
+
+        self.finalize(
+            filepath,
+            &[&light_buffer, &dark_buffer],
+            content,
+            "themed-png",
+        )?;
+        Ok(warnings)
+    }
+
+    /// Builds the `<img>` attributes for one variant of a themed image pair:
+    /// `id` gets `-{id_suffix}` appended and `extra_class` is merged into
+    /// `class` alongside the configured one.
+    fn themed_img_attrs(&self, id_suffix: &str, extra_class: &str) -> String {
+        let mut attrs = String::new();
         if !self.id.is_empty() {
-            content += &format!["id=\"{}\" ", self.id];
-        }
-        if !self.class.is_empty() {
-            content += &format!["class=\"{}\" ", self.class];
+            attrs += &format!["id=\"{}-{}\" ", self.id, id_suffix];
         }
+        let class = if self.class.is_empty() {
+            extra_class.to_owned()
+        } else {
+            format!["{} {}", extra_class, self.class]
+        };
+        attrs += &format!["class=\"{}\" ", class];
         if !self.alt.is_empty() {
-            content += &format!["alt=\"{}\" ", self.alt];
+            attrs += &format!["alt=\"{}\" ", self.alt];
         }
         if !self.title.is_empty() {
-            content += &format!["title=\"{}\" ", self.title];
+            attrs += &format!["title=\"{}\" ", self.title];
         }
         if !self.style.is_empty() {
-            content += &format!["style=\"{}\" ", self.style];
+            attrs += &format!["style=\"{}\" ", self.style];
         }
-        content += "/>";
+        attrs
+    }
+
+    /// Derives a CSS-safe identifier from `path`, used to scope the
+    /// `<style>` block [`generate_themed`][Self::generate_themed] emits so
+    /// multiple themed images on the same rustdoc page don't collide.
+    fn css_uid(&self) -> String {
+        self.path
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect()
+    }
+
+    /// Finishes the image, calling the generator function and saving the file,
+    /// the same as [`generate`][Self::generate] but producing an SVG instead
+    /// of a rasterized PNG.
+    ///
+    /// `generator` renders into a `String` the same way plotters'
+    /// `SVGBackend::with_string` does, writing a full `<svg>…</svg>` document.
+    ///
+    /// By default the SVG is embedded as raw inline markup, which rustdoc
+    /// renders crisply at any zoom level. Set [`svg_inline(false)`
+    /// ][Self::svg_inline] to embed it instead as a
+    /// `data:image/svg+xml;base64,…` `<img>`, matching [`generate`
+    /// ][Self::generate]'s output shape.
+    ///
+    /// The `wrapper` machinery applies identically to both cases. When
+    /// embedding inline, `id`, `class` and `style` are spliced into the root
+    /// `<svg>` tag and `title` is added as a child `<title>` element for
+    /// accessibility; `alt` has no inline-SVG equivalent and is ignored
+    /// unless `svg_inline` is `false`.
+    ///
+    /// Returns any non-fatal [`DocimaWarning`]s raised along the way (also
+    /// printed as `cargo:warning=` lines); since `alt` doesn't apply to
+    /// inline SVG, [`DocimaWarning::MissingAlt`] is only raised when
+    /// `svg_inline` is `false`.
+    pub fn generate_svg(
+        self,
+        generator: impl Fn(&mut String, u32, u32) -> StdResult<()>,
+    ) -> DocimaResult<Vec<DocimaWarning>> {
+        let filepath = self.prepare()?;
+
+        let mut svg = String::new();
+        generator(&mut svg, self.width, self.height)?;
+        let raw_svg = svg.clone();
+
+        let content = if self.svg_inline {
+            self.embed_svg_inline(svg)
+        } else {
+            let base64 = BASE64_MIME.encode(svg.as_bytes());
+            format![
+                "<img src=\"data:image/svg+xml;base64,\n{}\" {}/>",
+                base64,
+                self.img_attrs()
+            ]
+        };
 
         // add the wrapper HTML tag
-        if !self.wrapper.is_empty() {
-            let mut wrapper_open = format!["<{0} ", self.wrapper];
+        let content = self.wrap(content);
 
-            // add the wrapper attributes to the opening tag
-            if !self.wrapper_id.is_empty() {
-                wrapper_open += &format!["class=\"{}\" ", self.wrapper_id];
-            }
-            if !self.wrapper_class.is_empty() {
-                wrapper_open += &format!["class=\"{}\" ", self.wrapper_class];
-            }
-            if !self.wrapper_alt.is_empty() {
-                wrapper_open += &format!["alt=\"{}\" ", self.wrapper_alt];
+        let mut warnings = self.config_warnings();
+        if self.svg_inline {
+            warnings.retain(|warning| *warning != DocimaWarning::MissingAlt);
+        }
+        warnings.extend(Self::payload_warnings(content.len()));
+        if raw_svg.is_empty() {
+            warnings.push(DocimaWarning::EmptyOutput);
+        }
+        Self::emit_warnings(&warnings);
+
+        self.finalize(filepath, &[raw_svg.as_bytes()], content, "svg")?;
+        Ok(warnings)
+    }
+
+    /// Splices `id`/`class`/`style` into the root `<svg>` tag and inserts a
+    /// `<title>` child, then returns the resulting document.
+    fn embed_svg_inline(&self, mut svg: String) -> String {
+        if let Some(tag_end) = svg.find('>') {
+            let mut root_attrs = String::new();
+            if !self.id.is_empty() {
+                root_attrs += &format![" id=\"{}\"", self.id];
             }
-            if !self.wrapper_title.is_empty() {
-                wrapper_open += &format!["title=\"{}\" ", self.wrapper_title];
+            if !self.class.is_empty() {
+                root_attrs += &format![" class=\"{}\"", self.class];
             }
-            if !self.wrapper_style.is_empty() {
-                wrapper_open += &format!["style=\"{}\" ", self.wrapper_style];
+            if !self.style.is_empty() {
+                root_attrs += &format![" style=\"{}\"", self.style];
             }
-            // anchor specific attributes
-            if self.wrapper == "a" {
-                if !self.wrapper_href.is_empty() {
-                    wrapper_open += &format!["href=\"{}\" ", self.wrapper_href];
-                }
-                if !self.wrapper_target.is_empty() {
-                    wrapper_open += &format!["target=\"{}\" ", self.wrapper_target];
-                }
+            if !root_attrs.is_empty() {
+                svg.insert_str(tag_end, &root_attrs);
             }
-            wrapper_open += ">";
 
-            content = format!["{0}{1}</{2}>", wrapper_open, content, self.wrapper];
+            if !self.title.is_empty() {
+                let insert_at = svg.find('>').map(|i| i + 1).unwrap_or(0);
+                svg.insert_str(insert_at, &format!["<title>{}</title>", self.title]);
+            }
         }
-
-        // write the output string to the desired location only
-        let mut outfile = File::create(filepath)?;
-        write!(outfile, "{}", content)?;
-
-        Ok(())
+        svg
     }
 }
 
@@ -355,15 +916,76 @@ impl ImageFile {
         self
     }
 
-    /// Sets the wrapper tag `style` attribute.
+    /// Controls when an existing output file is rewritten.
     ///
-    /// If `false` the image will only be generated if the chosen output file
-    /// doesn't already exist.
-    /// If `true` the image will always be generated, and the file overwritten.
+    /// If `false` (the default) the generator still runs, but the file is
+    /// only rewritten when the computed `docima:sha256` content hash
+    /// differs from the one already embedded in it, keeping committed docs
+    /// diff-stable across identical `cargo doc` runs.
+    /// If `true` the file is always rewritten, regardless of the hash.
     pub fn overwrite(mut self, overwrite: bool) -> Self {
         self.overwrite = overwrite;
         self
     }
+
+    /// Sets whether [`generate_svg`][Self::generate_svg] embeds its output as
+    /// raw inline `<svg>` markup (`true`, the default) or as a
+    /// `data:image/svg+xml;base64,…` `<img>` (`false`).
+    ///
+    /// Has no effect on [`generate`][Self::generate].
+    pub fn svg_inline(mut self, svg_inline: bool) -> Self {
+        self.svg_inline = svg_inline;
+        self
+    }
+
+    /// Sets the number of frames [`generate_animated`][Self::generate_animated]
+    /// renders into an animated PNG.
+    pub fn frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Enables an alpha channel, switching the PNG buffer from RGB8 (3
+    /// bytes per pixel) to RGBA8 (4 bytes per pixel), so a generator can
+    /// leave pixels transparent instead of painting an opaque background.
+    ///
+    /// Applies to [`generate`][Self::generate], [`generate_animated`
+    /// ][Self::generate_animated] and [`generate_themed`
+    /// ][Self::generate_themed]; has no effect on [`generate_svg`
+    /// ][Self::generate_svg], which supports transparency natively.
+    ///
+    /// Only the color type is configurable; the PNG bit depth stays fixed
+    /// at [`png::BitDepth::Eight`], matching `plotters`' `BitMapBackend`
+    /// (`u8`-per-channel) output and every generator this crate ships
+    /// examples for.
+    pub fn alpha(mut self, alpha: bool) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the per-frame delay, in milliseconds, for
+    /// [`generate_animated`][Self::generate_animated].
+    ///
+    /// The APNG format stores this as a 16-bit numerator, so
+    /// [`generate_animated`][Self::generate_animated] errors if this exceeds
+    /// `u16::MAX` (65535 ms) rather than silently truncating it.
+    pub fn frame_delay(mut self, frame_delay: u32) -> Self {
+        self.frame_delay = frame_delay;
+        self
+    }
+
+    /// Opts into recording this image in the shared build manifest at
+    /// `target/docima-manifest.json`: its path, dimensions, format,
+    /// wrapper, attributes and content hash. Disabled by default.
+    ///
+    /// Re-generating the same `path` replaces its existing entry rather
+    /// than duplicating it, so the manifest always reflects the current
+    /// build and can drive cleanup of orphaned files or CI checks that
+    /// every committed image is still produced by the build script.
+    pub fn manifest(mut self, manifest: bool) -> Self {
+        self.manifest = manifest;
+        self
+    }
 }
 
 /// Returns a path relative to the root of the project.
@@ -372,3 +994,133 @@ fn root_path(relative: &str) -> String {
     path.push(relative);
     path.to_str().expect("path.to_str()").to_owned()
 }
+
+/// Escapes `"`, `\` and newlines for embedding `s` in a JSON string literal.
+fn json_escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| match c {
+            '"' => vec!['\\', '"'],
+            '\\' => vec!['\\', '\\'],
+            '\n' => vec!['\\', 'n'],
+            _ => vec![c],
+        })
+        .collect()
+}
+
+/// Splits `json` (expected to be a top-level JSON array of objects) into
+/// each object's raw JSON text, tracking brace depth and string literals so
+/// braces or commas inside string values don't confuse the split.
+fn split_json_objects(json: &str) -> Vec<String> {
+    let mut objects = Vec::new();
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = None;
+
+    for (i, c) in json.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(s) = start.take() {
+                        objects.push(json[s..=i].to_owned());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_escape_escapes_quotes_backslashes_and_newlines() {
+        assert_eq!(json_escape("plain"), "plain");
+        assert_eq!(json_escape("a \"quoted\" value"), "a \\\"quoted\\\" value");
+        assert_eq!(json_escape(r"C:\path"), r"C:\\path");
+        assert_eq!(json_escape("line1\nline2"), "line1\\nline2");
+    }
+
+    #[test]
+    fn split_json_objects_splits_top_level_objects_only() {
+        let json = r#"[
+          {"path":"a.html","hash":"1"},
+          {"path":"b.html","hash":"{nested}","note":"has a comma, and a brace }"}
+        ]"#;
+        let objects = split_json_objects(json);
+        assert_eq!(objects.len(), 2);
+        assert!(objects[0].contains("\"path\":\"a.html\""));
+        assert!(objects[1].contains("\"path\":\"b.html\""));
+        assert!(objects[1].contains("has a comma, and a brace }"));
+    }
+
+    #[test]
+    fn split_json_objects_ignores_braces_inside_string_literals() {
+        let json = r#"[{"path":"a","weird":"{ looks like json } but isn't"}]"#;
+        let objects = split_json_objects(json);
+        assert_eq!(objects.len(), 1);
+        assert!(objects[0].contains("looks like json"));
+    }
+
+    #[test]
+    fn split_json_objects_empty_array_yields_no_objects() {
+        assert!(split_json_objects("[]").is_empty());
+    }
+
+    #[test]
+    fn cached_hash_round_trips_through_a_written_file() {
+        let mut path = std::env::temp_dir();
+        path.push(format![
+            "docima-cached-hash-test-{:?}",
+            std::thread::current().id()
+        ]);
+        std::fs::write(&path, "<!-- docima:sha256=deadbeef -->\n<img/>").unwrap();
+
+        assert_eq!(ImageFile::cached_hash(&path), Some("deadbeef".to_owned()));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cached_hash_is_none_without_the_expected_comment() {
+        let mut path = std::env::temp_dir();
+        path.push(format![
+            "docima-cached-hash-test-missing-{:?}",
+            std::thread::current().id()
+        ]);
+        std::fs::write(&path, "<img/>").unwrap();
+
+        assert_eq!(ImageFile::cached_hash(&path), None);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn cached_hash_is_none_for_a_missing_file() {
+        let mut path = std::env::temp_dir();
+        path.push("docima-cached-hash-test-does-not-exist");
+        assert_eq!(ImageFile::cached_hash(&path), None);
+    }
+}